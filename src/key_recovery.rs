@@ -0,0 +1,216 @@
+// Reconstructs and validates RSA keys from partial material, in the spirit
+// of the TPM2_TestParms "test key" operation: given enough of (n, p, q, e, d)
+// to pin down the rest, rebuild the remaining parameters, or detect that the
+// supplied parameters don't actually form a valid key.
+
+use rug::{rand::RandState, Integer};
+
+use crate::rsa_gmp::PrivateKey;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RecoveryError {
+    /// `p` does not evenly divide `n`, or is a degenerate value (`0`, `1`,
+    /// or `n` itself) that would make the reconstructed key meaningless.
+    NotADivisor,
+    /// The randomized factorization did not find a nontrivial factor within
+    /// its attempt budget; callers may retry since it re-seeds each time.
+    FactorizationFailed,
+    /// `e*d - 1` is not a positive number, so it cannot be the even multiple
+    /// of the group order the factorization algorithm requires.
+    InvalidExponents,
+}
+
+/// Reconstructs a full private key from the modulus `n`, one known prime
+/// factor `p`, and the public exponent `e`.
+pub fn recover_from_prime(n: &Integer, p: &Integer, e: &Integer) -> Result<PrivateKey, RecoveryError> {
+    if *p <= 1 || p == n {
+        return Err(RecoveryError::NotADivisor);
+    }
+
+    let q = n.clone() / p.clone();
+    if q.clone() * p.clone() != *n {
+        return Err(RecoveryError::NotADivisor);
+    }
+
+    let t = (p.clone() - 1).lcm(&(q.clone() - 1));
+    let d = e.clone().invert(&t).unwrap();
+
+    Ok(PrivateKey::from_parts(n.clone(), e.clone(), d, Some(p.clone()), Some(q)))
+}
+
+/// Recovers the prime factorization of `n` from a full `(n, e, d)` key pair,
+/// using the randomized algorithm described in Boneh's "Twenty Years of
+/// Attacks on the RSA Cryptosystem" (Fact 1): `e*d - 1` is a multiple of the
+/// group order, so a random element's order reveals a nontrivial square
+/// root of 1 mod n, which is not possible mod a prime.
+pub fn recover_factors(n: &Integer, e: &Integer, d: &Integer) -> Result<(Integer, Integer), RecoveryError> {
+    let k: Integer = e.clone() * d.clone() - 1;
+    if k <= 0 {
+        return Err(RecoveryError::InvalidExponents);
+    }
+
+    let mut r = k;
+    let mut s = 0u32;
+    while r.is_even() {
+        r >>= 1;
+        s += 1;
+    }
+
+    let mut rand_state = RandState::new();
+    rand_state.seed(&crate::oaep::be_bytes_to_integer(&crate::oaep::random_bytes(32)));
+    let n_minus_1 = n.clone() - 1;
+    let g_boundary = n.clone() - 3;
+
+    for _ in 0..100 {
+        // g in [2, n-2]
+        let g = Integer::from(2) + g_boundary.clone().random_below(&mut rand_state);
+        let mut y = g.pow_mod(&r, n).unwrap();
+
+        if y == 1 || y == n_minus_1 {
+            continue;
+        }
+
+        // g^(r * 2^s) == 1, so up to s squarings reach it
+        for _ in 0..s {
+            let y_squared = y.clone().pow_mod(&Integer::from(2), n).unwrap();
+
+            if y_squared == 1 {
+                let factor = (y.clone() - 1).gcd(n);
+                if factor != 1 && factor != *n {
+                    let other = n.clone() / factor.clone();
+                    return Ok((factor, other));
+                }
+                break;
+            }
+
+            if y_squared == n_minus_1 {
+                break;
+            }
+
+            y = y_squared;
+        }
+    }
+
+    Err(RecoveryError::FactorizationFailed)
+}
+
+/// Checks that `(p, q, n, e, d)` form a consistent RSA key: `p*q == n`, `e`
+/// is coprime to `t = lcm(p-1, q-1)`, and `e*d == 1 (mod t)`.
+pub fn validate_key_pair(p: &Integer, q: &Integer, n: &Integer, e: &Integer, d: &Integer) -> bool {
+    if p.clone() * q.clone() != *n {
+        return false;
+    }
+
+    let t = (p.clone() - 1).lcm(&(q.clone() - 1));
+    if e.clone().gcd(&t) != 1 {
+        return false;
+    }
+
+    (e.clone() * d.clone()).modulo(&t) == 1
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::rsa_gmp::decrypt;
+
+    // Small, fixed key so factorization-style tests stay fast.
+    const P: u32 = 653;
+    const Q: u32 = 877;
+    const E: u32 = 13;
+
+    #[test]
+    fn recover_from_prime_matches_generated_key() {
+        let n = Integer::from(P) * Integer::from(Q);
+        let e = Integer::from(E);
+
+        let private_key = recover_from_prime(&n, &Integer::from(P), &e).unwrap();
+
+        let m = Integer::from(12345);
+        let c = m.clone().pow_mod(&e, &n).unwrap();
+        assert_eq!(decrypt(c, &private_key), m);
+    }
+
+    #[test]
+    fn recover_from_prime_rejects_non_divisor() {
+        let n = Integer::from(P) * Integer::from(Q);
+        let e = Integer::from(E);
+
+        let result = recover_from_prime(&n, &Integer::from(101), &e);
+        assert_eq!(result.unwrap_err(), RecoveryError::NotADivisor);
+    }
+
+    #[test]
+    fn recover_from_prime_rejects_degenerate_p() {
+        let n = Integer::from(P) * Integer::from(Q);
+        let e = Integer::from(E);
+
+        // p = 1 trivially satisfies q*p == n (with q = n), but is not a
+        // real factor and must not be allowed to reach the modular inverse
+        assert_eq!(
+            recover_from_prime(&n, &Integer::from(1), &e).unwrap_err(),
+            RecoveryError::NotADivisor
+        );
+        assert_eq!(
+            recover_from_prime(&n, &Integer::from(0), &e).unwrap_err(),
+            RecoveryError::NotADivisor
+        );
+        assert_eq!(
+            recover_from_prime(&n, &n, &e).unwrap_err(),
+            RecoveryError::NotADivisor
+        );
+    }
+
+    #[test]
+    fn recover_factors_rejects_non_positive_exponent_product() {
+        let n = Integer::from(P) * Integer::from(Q);
+
+        // e*d == 1, so k = e*d - 1 == 0, which would otherwise spin forever
+        // halving zero looking for an odd factor
+        assert_eq!(
+            recover_factors(&n, &Integer::from(1), &Integer::from(1)).unwrap_err(),
+            RecoveryError::InvalidExponents
+        );
+
+        // e*d == 0, so k = -1, which would otherwise drive a negative
+        // exponent into pow_mod against a composite modulus
+        assert_eq!(
+            recover_factors(&n, &Integer::from(0), &Integer::from(5)).unwrap_err(),
+            RecoveryError::InvalidExponents
+        );
+    }
+
+    #[test]
+    fn recover_factors_finds_p_and_q() {
+        let n = Integer::from(P) * Integer::from(Q);
+        let t = (Integer::from(P) - 1).lcm(&(Integer::from(Q) - 1));
+        let e = Integer::from(E);
+        let d = e.clone().invert(&t).unwrap();
+
+        let (p, q) = recover_factors(&n, &e, &d).unwrap();
+        let mut factors = [p, q];
+        factors.sort();
+        assert_eq!(factors, [Integer::from(P), Integer::from(Q)]);
+    }
+
+    #[test]
+    fn validate_key_pair_accepts_consistent_key() {
+        let n = Integer::from(P) * Integer::from(Q);
+        let t = (Integer::from(P) - 1).lcm(&(Integer::from(Q) - 1));
+        let e = Integer::from(E);
+        let d = e.clone().invert(&t).unwrap();
+
+        assert!(validate_key_pair(&Integer::from(P), &Integer::from(Q), &n, &e, &d));
+    }
+
+    #[test]
+    fn validate_key_pair_rejects_mismatched_n() {
+        let n = Integer::from(P) * Integer::from(Q) + 1;
+        let t = (Integer::from(P) - 1).lcm(&(Integer::from(Q) - 1));
+        let e = Integer::from(E);
+        let d = e.clone().invert(&t).unwrap();
+
+        assert!(!validate_key_pair(&Integer::from(P), &Integer::from(Q), &n, &e, &d));
+    }
+}
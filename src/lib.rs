@@ -5,6 +5,12 @@
 // https://medium.com/snips-ai/prime-number-generation-2a02f28508ff
 // https://github.com/AtropineTears/num-primes
 
+pub mod rsa_gmp;
+pub mod oaep;
+pub mod pss;
+pub mod key_recovery;
+pub mod pkcs1;
+
 use num::BigInt;
 use openssl::bn::BigNum;
 use rug::Integer;
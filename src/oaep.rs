@@ -0,0 +1,191 @@
+// RSAES-OAEP, RFC 8017, Section 7.1
+// https://datatracker.ietf.org/doc/html/rfc8017#section-7.1
+
+use rug::{integer::Order, Integer};
+use sha2::{Digest, Sha256};
+
+use crate::rsa_gmp::{decrypt as raw_decrypt, encrypt as raw_encrypt, PrivateKey, PublicKey};
+
+/// SHA-256 output length, in bytes
+const HASH_LEN: usize = 32;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum OaepError {
+    /// The message is longer than `k - 2*hLen - 2` bytes for this key size.
+    MessageTooLong,
+    /// Decryption failed: wrong key, corrupted ciphertext, or padding mismatch.
+    /// Deliberately not more specific, so a caller cannot use the error to
+    /// distinguish padding failures from other failures (a padding oracle).
+    DecryptionError,
+}
+
+/// Encrypts `message` for `public_key` using RSAES-OAEP with SHA-256 and MGF1.
+///
+/// `label` is the optional `L` parameter from RFC 8017; pass `&[]` if the
+/// scheme is used without one.
+pub fn encrypt(message: &[u8], label: &[u8], public_key: &PublicKey) -> Result<Integer, OaepError> {
+    let k = public_key.modulus_len_bytes();
+    if k < 2 * HASH_LEN + 2 || message.len() > k - 2 * HASH_LEN - 2 {
+        return Err(OaepError::MessageTooLong);
+    }
+
+    let l_hash = Sha256::digest(label);
+    let ps_len = k - message.len() - 2 * HASH_LEN - 2;
+
+    // DB = lHash || PS || 0x01 || M
+    let mut db = Vec::with_capacity(k - HASH_LEN - 1);
+    db.extend_from_slice(&l_hash);
+    db.extend(std::iter::repeat(0u8).take(ps_len));
+    db.push(1);
+    db.extend_from_slice(message);
+
+    let seed = random_bytes(HASH_LEN);
+    let masked_db = xor(&db, &mgf1(&seed, db.len()));
+    let masked_seed = xor(&seed, &mgf1(&masked_db, HASH_LEN));
+
+    // EM = 0x00 || maskedSeed || maskedDB
+    let mut em = Vec::with_capacity(k);
+    em.push(0);
+    em.extend_from_slice(&masked_seed);
+    em.extend_from_slice(&masked_db);
+
+    Ok(raw_encrypt(be_bytes_to_integer(&em), public_key))
+}
+
+/// Decrypts an RSAES-OAEP ciphertext produced by [`encrypt`].
+pub fn decrypt(ciphertext: Integer, label: &[u8], private_key: &PrivateKey) -> Result<Vec<u8>, OaepError> {
+    let k = private_key.modulus_len_bytes();
+    if k < 2 * HASH_LEN + 2 {
+        return Err(OaepError::DecryptionError);
+    }
+
+    let m = raw_decrypt(ciphertext, private_key);
+    let em = integer_to_be_bytes(&m, k);
+
+    let y = em[0];
+    let masked_seed = &em[1..1 + HASH_LEN];
+    let masked_db = &em[1 + HASH_LEN..];
+
+    let seed = xor(masked_seed, &mgf1(masked_db, HASH_LEN));
+    let db = xor(masked_db, &mgf1(&seed, masked_db.len()));
+
+    let l_hash = Sha256::digest(label);
+    let (db_l_hash, rest) = db.split_at(HASH_LEN);
+
+    // Scan the whole of `rest` rather than stopping at the first 0x01, so
+    // the time taken does not reveal where (or whether) the separator was
+    // found to an attacker probing the decryption oracle.
+    let mut separator_index = rest.len();
+    let mut separator_found = 0u8;
+    let mut ps_ok = 1u8;
+    for (i, &byte) in rest.iter().enumerate() {
+        let is_separator = (byte == 1) as u8 & (1 - separator_found);
+        separator_index = if is_separator == 1 { i } else { separator_index };
+        separator_found |= is_separator;
+        ps_ok &= separator_found | (byte == 0) as u8;
+    }
+
+    let valid = y == 0 && ct_eq(db_l_hash, &l_hash) && separator_found == 1 && ps_ok == 1;
+    if !valid {
+        return Err(OaepError::DecryptionError);
+    }
+
+    Ok(rest[separator_index + 1..].to_vec())
+}
+
+/// MGF1 mask generation function, RFC 8017 Appendix B.2.1, using SHA-256.
+pub(crate) fn mgf1(seed: &[u8], len: usize) -> Vec<u8> {
+    let mut output = Vec::with_capacity(len + HASH_LEN);
+    let mut counter: u32 = 0;
+    while output.len() < len {
+        let mut hasher = Sha256::new();
+        hasher.update(seed);
+        hasher.update(counter.to_be_bytes());
+        output.extend_from_slice(&hasher.finalize());
+        counter += 1;
+    }
+    output.truncate(len);
+    output
+}
+
+pub(crate) fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter().zip(b.iter()).map(|(x, y)| x ^ y).collect()
+}
+
+/// Constant-time byte-slice comparison: every byte is compared regardless
+/// of earlier mismatches.
+pub(crate) fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+pub(crate) fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    openssl::rand::rand_bytes(&mut bytes).unwrap();
+    bytes
+}
+
+pub(crate) fn integer_to_be_bytes(value: &Integer, len: usize) -> Vec<u8> {
+    let digits = value.to_digits::<u8>(Order::Msf);
+    let mut bytes = vec![0u8; len - digits.len()];
+    bytes.extend_from_slice(&digits);
+    bytes
+}
+
+pub(crate) fn be_bytes_to_integer(bytes: &[u8]) -> Integer {
+    Integer::from_digits(bytes, Order::Msf)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::rsa_gmp::generate_keys;
+
+    #[test]
+    fn oaep_round_trip() {
+        let (public_key, private_key) = generate_keys(2048, None, None);
+
+        let message = b"the quick brown fox jumps over the lazy dog";
+        let ciphertext = encrypt(message, b"", &public_key).unwrap();
+        let plaintext = decrypt(ciphertext, b"", &private_key).unwrap();
+
+        assert_eq!(plaintext, message);
+    }
+
+    #[test]
+    fn oaep_is_randomized() {
+        let (public_key, _) = generate_keys(2048, None, None);
+
+        let message = b"same message, different ciphertexts";
+        let c1 = encrypt(message, b"", &public_key).unwrap();
+        let c2 = encrypt(message, b"", &public_key).unwrap();
+
+        assert_ne!(c1, c2);
+    }
+
+    #[test]
+    fn oaep_rejects_message_too_long() {
+        let (public_key, _) = generate_keys(2048, None, None);
+
+        let k = public_key.modulus_len_bytes();
+        let message = vec![0u8; k - 2 * HASH_LEN - 1];
+
+        assert_eq!(encrypt(&message, b"", &public_key), Err(OaepError::MessageTooLong));
+    }
+
+    #[test]
+    fn oaep_rejects_mismatched_label() {
+        let (public_key, private_key) = generate_keys(2048, None, None);
+
+        let message = b"labelled message";
+        let ciphertext = encrypt(message, b"expected-label", &public_key).unwrap();
+
+        assert_eq!(
+            decrypt(ciphertext, b"wrong-label", &private_key),
+            Err(OaepError::DecryptionError)
+        );
+    }
+}
@@ -0,0 +1,191 @@
+// RSASSA-PSS, RFC 8017, Section 8.1
+// https://datatracker.ietf.org/doc/html/rfc8017#section-8.1
+
+use rug::{integer::Order, Integer};
+use sha2::{Digest, Sha256};
+
+use crate::oaep::{be_bytes_to_integer, ct_eq, integer_to_be_bytes, mgf1, random_bytes, xor};
+use crate::rsa_gmp::{decrypt as raw_decrypt, encrypt as raw_encrypt, PrivateKey, PublicKey};
+
+/// SHA-256 output length, in bytes
+const HASH_LEN: usize = 32;
+
+/// Salt length, in bytes; the common choice of `sLen == hLen`.
+const SALT_LEN: usize = HASH_LEN;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PssError {
+    /// The key is too small to hold an encoded message of this `sLen`/`hLen`.
+    Encoding,
+    /// The signature does not verify against the message and key.
+    VerificationFailed,
+}
+
+/// Signs `message` with `private_key` using RSASSA-PSS with SHA-256 and MGF1.
+pub fn sign(message: &[u8], private_key: &PrivateKey) -> Result<Integer, PssError> {
+    let em_bits = private_key.modulus_bits() - 1;
+    let em = emsa_pss_encode(message, em_bits)?;
+    Ok(raw_decrypt(be_bytes_to_integer(&em), private_key))
+}
+
+/// Verifies `signature` over `message` under `public_key`.
+pub fn verify(message: &[u8], signature: &Integer, public_key: &PublicKey) -> Result<(), PssError> {
+    let em_bits = public_key.modulus_bits() - 1;
+    let em_len = (em_bits as usize + 7) / 8;
+
+    let m = raw_encrypt(signature.clone(), public_key);
+    // `m` can need a full `modulus_len_bytes()` bytes, one more than `em_len`
+    // when `modulus_bits() % 8 == 1`; that doesn't fit the PSS encoding.
+    if m.to_digits::<u8>(Order::Msf).len() > em_len {
+        return Err(PssError::VerificationFailed);
+    }
+    let em = integer_to_be_bytes(&m, em_len);
+
+    emsa_pss_verify(message, &em, em_bits)
+}
+
+/// EMSA-PSS-ENCODE, RFC 8017 Section 9.1.1.
+fn emsa_pss_encode(message: &[u8], em_bits: u32) -> Result<Vec<u8>, PssError> {
+    let em_len = (em_bits as usize + 7) / 8;
+    if em_len < HASH_LEN + SALT_LEN + 2 {
+        return Err(PssError::Encoding);
+    }
+
+    let m_hash = Sha256::digest(message);
+    let salt = random_bytes(SALT_LEN);
+
+    // M' = 8 zero octets || mHash || salt
+    let mut m_prime = Vec::with_capacity(8 + HASH_LEN + SALT_LEN);
+    m_prime.extend_from_slice(&[0u8; 8]);
+    m_prime.extend_from_slice(&m_hash);
+    m_prime.extend_from_slice(&salt);
+    let h = Sha256::digest(&m_prime);
+
+    // DB = PS(zeros) || 0x01 || salt
+    let ps_len = em_len - SALT_LEN - HASH_LEN - 2;
+    let mut db = Vec::with_capacity(em_len - HASH_LEN - 1);
+    db.extend(std::iter::repeat(0u8).take(ps_len));
+    db.push(1);
+    db.extend_from_slice(&salt);
+
+    let mut masked_db = xor(&db, &mgf1(&h, db.len()));
+    clear_leftmost_bits(&mut masked_db, 8 * em_len - em_bits as usize);
+
+    let mut em = Vec::with_capacity(em_len);
+    em.extend_from_slice(&masked_db);
+    em.extend_from_slice(&h);
+    em.push(0xbc);
+
+    Ok(em)
+}
+
+/// EMSA-PSS-VERIFY, RFC 8017 Section 9.1.2.
+fn emsa_pss_verify(message: &[u8], em: &[u8], em_bits: u32) -> Result<(), PssError> {
+    let em_len = (em_bits as usize + 7) / 8;
+    if em.len() != em_len
+        || em_len < HASH_LEN + SALT_LEN + 2
+        || em[em_len - 1] != 0xbc
+    {
+        return Err(PssError::VerificationFailed);
+    }
+
+    let masked_db = &em[..em_len - HASH_LEN - 1];
+    let h = &em[em_len - HASH_LEN - 1..em_len - 1];
+
+    let top_bits = 8 * em_len - em_bits as usize;
+    if top_bits > 0 && masked_db[0] >> (8 - top_bits) != 0 {
+        return Err(PssError::VerificationFailed);
+    }
+
+    let mut db = xor(masked_db, &mgf1(h, masked_db.len()));
+    clear_leftmost_bits(&mut db, top_bits);
+
+    let ps_len = em_len - SALT_LEN - HASH_LEN - 2;
+    if !db[..ps_len].iter().all(|&byte| byte == 0) || db[ps_len] != 1 {
+        return Err(PssError::VerificationFailed);
+    }
+    let salt = &db[ps_len + 1..];
+
+    let m_hash = Sha256::digest(message);
+    let mut m_prime = Vec::with_capacity(8 + HASH_LEN + SALT_LEN);
+    m_prime.extend_from_slice(&[0u8; 8]);
+    m_prime.extend_from_slice(&m_hash);
+    m_prime.extend_from_slice(salt);
+    let h_prime = Sha256::digest(&m_prime);
+
+    if ct_eq(h, &h_prime) {
+        Ok(())
+    } else {
+        Err(PssError::VerificationFailed)
+    }
+}
+
+/// Zeroes the leftmost `bits` bits of `data`, as required by RFC 8017 to
+/// keep the encoded message strictly below the modulus.
+fn clear_leftmost_bits(data: &mut [u8], bits: usize) {
+    if bits == 0 || data.is_empty() {
+        return;
+    }
+    data[0] &= 0xff >> bits;
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::rsa_gmp::generate_keys;
+
+    #[test]
+    fn pss_round_trip() {
+        let (public_key, private_key) = generate_keys(2048, None, None);
+
+        let message = b"the quick brown fox jumps over the lazy dog";
+        let signature = sign(message, &private_key).unwrap();
+
+        assert_eq!(verify(message, &signature, &public_key), Ok(()));
+    }
+
+    #[test]
+    fn pss_rejects_tampered_message() {
+        let (public_key, private_key) = generate_keys(2048, None, None);
+
+        let signature = sign(b"original message", &private_key).unwrap();
+
+        assert_eq!(
+            verify(b"tampered message", &signature, &public_key),
+            Err(PssError::VerificationFailed)
+        );
+    }
+
+    #[test]
+    fn pss_verify_rejects_oversized_signature_instead_of_panicking() {
+        // n = 257 has 9 significant bits (9 % 8 == 1), so em_len is one byte
+        // short of modulus_len_bytes(); an adversarial signature whose
+        // raw_encrypt result needs the full 2 bytes used to panic instead of
+        // returning VerificationFailed.
+        let n = Integer::from(257);
+        let e = Integer::from(3);
+        let public_key = PublicKey::from_parts(n.clone(), e.clone());
+
+        let signature = (0..257)
+            .map(Integer::from)
+            .find(|s| raw_encrypt(s.clone(), &public_key) >= 256)
+            .expect("some residue's cube mod 257 reaches the top byte");
+
+        assert_eq!(
+            verify(b"anything", &signature, &public_key),
+            Err(PssError::VerificationFailed)
+        );
+    }
+
+    #[test]
+    fn pss_signatures_are_randomized() {
+        let (_, private_key) = generate_keys(2048, None, None);
+
+        let message = b"same message, different signatures";
+        let s1 = sign(message, &private_key).unwrap();
+        let s2 = sign(message, &private_key).unwrap();
+
+        assert_ne!(s1, s2);
+    }
+}
@@ -1,30 +1,145 @@
 // https://gitlab.com/tspiteri/rug       
 // https://gmplib.org/ 
 
-use rug::{Integer, Float, rand::RandState};
+use rug::{integer::Order, Float, Integer, rand::RandState};
 
 pub struct PublicKey {
     e: Integer,
     n: Integer,
 }
 
+#[derive(Clone)]
 pub struct PrivateKey {
     d: Integer,
     n: Integer,
+    e: Integer,
+    crt: Option<CrtParams>,
+}
+
+/// Chinese Remainder Theorem parameters for the private key.
+/// Carrying these lets `decrypt` recombine two half-width modular
+/// exponentiations instead of one full-width one, which is roughly
+/// 3-4x faster for large moduli.
+#[derive(Clone)]
+struct CrtParams {
+    p: Integer,
+    q: Integer,
+    dp: Integer,
+    dq: Integer,
+    qinv: Integer,
+}
+
+impl Drop for PrivateKey {
+    /// Zeroes the secret limbs before the allocation is freed, so key
+    /// fragments are not left behind in the heap for long-lived processes
+    /// holding many keys. The public key has no secrets and is unaffected.
+    fn drop(&mut self) {
+        zeroize(&mut self.d);
+        if let Some(crt) = &mut self.crt {
+            zeroize(&mut crt.p);
+            zeroize(&mut crt.q);
+            zeroize(&mut crt.dp);
+            zeroize(&mut crt.dq);
+            zeroize(&mut crt.qinv);
+        }
+    }
+}
+
+/// Overwrites `value`'s current allocation with zero bytes before resetting
+/// it, rather than simply assigning zero (which could leave the prior limbs
+/// intact in memory until the allocation is reused or freed).
+fn zeroize(value: &mut Integer) {
+    let len_bytes = ((value.significant_bits() + 7) / 8) as usize;
+    if len_bytes > 0 {
+        value.assign_digits(&vec![0u8; len_bytes], Order::Msf);
+    }
+    *value = Integer::new();
+}
+
+impl PublicKey {
+    /// The length in bytes, `k`, of the modulus `n` (the RSA block size).
+    pub(crate) fn modulus_len_bytes(&self) -> usize {
+        ((self.modulus_bits() + 7) / 8) as usize
+    }
+
+    /// The length in bits of the modulus `n`.
+    pub(crate) fn modulus_bits(&self) -> u32 {
+        self.n.significant_bits()
+    }
+
+    /// Builds a public key from an already-known modulus and exponent, e.g.
+    /// when importing one from an encoded key file.
+    pub fn from_parts(n: Integer, e: Integer) -> PublicKey {
+        PublicKey { e, n }
+    }
+
+    /// Returns `(n, e)`.
+    pub(crate) fn parts(&self) -> (&Integer, &Integer) {
+        (&self.n, &self.e)
+    }
+}
+
+impl PrivateKey {
+    /// The length in bytes, `k`, of the modulus `n` (the RSA block size).
+    pub(crate) fn modulus_len_bytes(&self) -> usize {
+        ((self.modulus_bits() + 7) / 8) as usize
+    }
+
+    /// The length in bits of the modulus `n`.
+    pub(crate) fn modulus_bits(&self) -> u32 {
+        self.n.significant_bits()
+    }
+
+    /// Builds a private key from already-known parameters, e.g. when a key
+    /// is reconstructed from partial material rather than freshly generated.
+    /// Computes the CRT parameters when both primes are supplied.
+    pub fn from_parts(n: Integer, e: Integer, d: Integer, p: Option<Integer>, q: Option<Integer>) -> PrivateKey {
+        let crt = match (p, q) {
+            (Some(p), Some(q)) => {
+                let dp = d.clone().modulo(&(p.clone() - 1));
+                let dq = d.clone().modulo(&(q.clone() - 1));
+                let qinv = q.clone().invert(&p).unwrap();
+                Some(CrtParams { p, q, dp, dq, qinv })
+            }
+            _ => None,
+        };
+        PrivateKey { d, n, e, crt }
+    }
+
+    /// Returns `(n, e, d)`.
+    pub(crate) fn parts(&self) -> (&Integer, &Integer, &Integer) {
+        (&self.n, &self.e, &self.d)
+    }
+
+    /// Returns `(p, q, dp, dq, qinv)` when the CRT parameters are known.
+    pub(crate) fn crt_parts(&self) -> Option<(&Integer, &Integer, &Integer, &Integer, &Integer)> {
+        self.crt
+            .as_ref()
+            .map(|crt| (&crt.p, &crt.q, &crt.dp, &crt.dq, &crt.qinv))
+    }
 }
 
 /// Returns generated RSA keys
 /// RSA key length is the length of the modulus n in bits
-/// 
+///
 /// # Arguments
-/// 
+///
 /// * `nlen` - the appropriate length in bits for the desired security strength
-pub fn generate_keys(nlen: u16) -> (PublicKey, PrivateKey) {
+/// * `mr_rounds` - number of Miller-Rabin rounds used to test each prime
+///   candidate; `None` uses the FIPS.186-4 default for `nlen`
+/// * `seed` - optional RNG seed, for reproducible key generation in tests
+pub fn generate_keys(nlen: u16, mr_rounds: Option<u32>, seed: Option<u64>) -> (PublicKey, PrivateKey) {
     // Key generation
     // https://datatracker.ietf.org/doc/html/rfc2313#section-6
 
+    let mut rand_state = RandState::new();
+    if let Some(seed) = seed {
+        rand_state.seed(&Integer::from(seed));
+    }
+    let mr_rounds = mr_rounds.unwrap_or_else(|| rsa_fips_mr_rounds(nlen));
+
     // 1. Choose two distinct primes p and q
-    let (p, q) = rsa_primes_p_q(nlen);
+    let (p, q) = rsa_primes_p_q(nlen, mr_rounds, &mut rand_state);
 
     // 2. Compute the modulus, n = p * q
     let n = p.clone() * q.clone();
@@ -32,7 +147,7 @@ pub fn generate_keys(nlen: u16) -> (PublicKey, PrivateKey) {
     // 3. Compute the totient, t
     let p_1: Integer = p.clone() - 1;
     let q_1: Integer = q.clone() - 1;
-    let t = p_1.lcm(&q_1);
+    let t = p_1.clone().lcm(&q_1);
 
     // 4. Choose any number 1 < e < t that is coprime to t
     // Choosing a prime number for e leaves us only to check that e is not a divisor of t
@@ -41,42 +156,130 @@ pub fn generate_keys(nlen: u16) -> (PublicKey, PrivateKey) {
     // 5. Compute d
     let d = e.clone().invert(&t).unwrap();
 
+    // precompute the CRT parameters so decryption can use the faster path
+    let dp = d.clone().modulo(&p_1);
+    let dq = d.clone().modulo(&q_1);
+    let qinv = q.clone().invert(&p).unwrap();
+
     // 6. public key is (e, n)
-    let public_key = PublicKey { e, n: n.clone() };
+    let public_key = PublicKey { e: e.clone(), n: n.clone() };
 
-    // 7. private key is (d, n)
-    let private_key = PrivateKey { d, n: n.clone() };
+    // 7. private key is (d, n), plus the CRT parameters
+    let private_key = PrivateKey {
+        d,
+        n,
+        e,
+        crt: Some(CrtParams { p, q, dp, dq, qinv }),
+    };
 
     (public_key, private_key)
 }
 
 /// FIPS.186-4, Section: B.3.1 Criteria for IFC Key Pairs
-/// 
+///
 /// sqrt(2)*2^((nlen/2)-1) <= p <= 2^(nlen/2)-1
-/// 
+///
 /// sqrt(2)*2^((nlen/2)-1) <= q <= 2^(nlen/2)-1
-/// 
-/// |p - q| > 2^((nlen/2)-100)  
-/// 
+///
+/// |p - q| > 2^((nlen/2)-100)
+///
 /// where nlen is the appropriate length for the desired security strength
-fn rsa_primes_p_q(nlen: u16) -> (Integer, Integer){
-
-    let mut rand_state = RandState::new();
+fn rsa_primes_p_q(nlen: u16, mr_rounds: u32, rand_state: &mut RandState) -> (Integer, Integer){
 
     let fips_min = rsa_fips_key_constraint_min(nlen);
     let fips_max = rsa_fips_key_constraint_max(nlen);
 
-    // compute fips_min <= p <= fips_max
-    let boundary = fips_max.clone() - fips_min.clone();
+    let p = rsa_probable_prime(&fips_min, &fips_max, mr_rounds, rand_state);
+
+    // |p - q| > 2^((nlen/2)-100); for small nlen (as used in tests) the
+    // exponent goes negative, in which case any two distinct primes qualify
+    let half_minus_100 = nlen as i32 / 2 - 100;
+    let diff_min = if half_minus_100 > 0 {
+        Integer::from(1) << half_minus_100 as u32
+    } else {
+        Integer::from(0)
+    };
+
+    let q = loop {
+        let candidate = rsa_probable_prime(&fips_min, &fips_max, mr_rounds, rand_state);
+        let diff = (candidate.clone() - p.clone()).abs();
+        if diff > diff_min {
+            break candidate;
+        }
+    };
 
-    let p_random = fips_min.clone() + boundary.clone().random_below(&mut rand_state);
-    let p = p_random.next_prime();
+    (p, q)
+}
 
-    // compute fips_min <= q <= fips_max
-    let q_random = fips_min.clone() + boundary.clone().random_below(&mut rand_state);
-    let q = q_random.next_prime();
+/// Draws odd candidates uniformly from `[min, max]` and returns the first
+/// that passes `mr_rounds` rounds of the Miller-Rabin primality test.
+fn rsa_probable_prime(min: &Integer, max: &Integer, mr_rounds: u32, rand_state: &mut RandState) -> Integer {
+    let boundary = max.clone() - min.clone();
+    loop {
+        let mut candidate = min.clone() + boundary.clone().random_below(rand_state);
+        candidate.set_bit(0, true);
+        if candidate <= *max && rsa_miller_rabin(&candidate, mr_rounds, rand_state) {
+            return candidate;
+        }
+    }
+}
 
-    (p, q)
+/// FIPS.186-4, Appendix C.3.1, the Miller-Rabin probabilistic primality test.
+///
+/// Writes `candidate - 1 = 2^s * d` with `d` odd, then for each of
+/// `mr_rounds` random bases `a` checks whether `a^d mod candidate` is `1` or
+/// `candidate - 1`, repeatedly squaring up to `s - 1` times looking for
+/// `candidate - 1`. A base for which neither occurs proves `candidate`
+/// composite.
+fn rsa_miller_rabin(candidate: &Integer, mr_rounds: u32, rand_state: &mut RandState) -> bool {
+    if *candidate < 4 {
+        return *candidate == 2 || *candidate == 3;
+    }
+    if candidate.is_even() {
+        return false;
+    }
+
+    let candidate_1 = candidate.clone() - 1;
+    let mut d = candidate_1.clone();
+    let mut s = 0u32;
+    while d.is_even() {
+        d >>= 1;
+        s += 1;
+    }
+
+    let a_boundary = candidate.clone() - 3;
+    'rounds: for _ in 0..mr_rounds {
+        // a in [2, candidate-2]
+        let a = Integer::from(2) + a_boundary.clone().random_below(rand_state);
+        let mut x = a.pow_mod(&d, candidate).unwrap();
+
+        if x == 1 || x == candidate_1 {
+            continue;
+        }
+
+        for _ in 0..s.saturating_sub(1) {
+            x = x.pow_mod(&Integer::from(2), candidate).unwrap();
+            if x == candidate_1 {
+                continue 'rounds;
+            }
+        }
+
+        return false;
+    }
+
+    true
+}
+
+/// FIPS.186-4, Appendix C.2/C.3: minimum number of Miller-Rabin rounds for a
+/// 2^-100 error probability, indexed by the bit length of each prime factor.
+fn rsa_fips_mr_rounds(nlen: u16) -> u32 {
+    match nlen / 2 {
+        0..=511 => 56,
+        512..=767 => 40,
+        768..=1023 => 38,
+        1024..=1535 => 17,
+        _ => 5,
+    }
 }
 
 fn rsa_fips_key_constraint_min(nlen: u16) -> Integer {
@@ -96,7 +299,27 @@ pub fn encrypt(m: Integer, public_key: &PublicKey) -> Integer {
 }
 
 pub fn decrypt(c: Integer, private_key: &PrivateKey) -> Integer {
-    c.pow_mod(&private_key.d, &private_key.n).unwrap()
+    match &private_key.crt {
+        Some(crt) => decrypt_crt(&c, crt),
+        None => c.pow_mod(&private_key.d, &private_key.n).unwrap(),
+    }
+}
+
+/// Decrypts via CRT recombination (Garner's formula):
+///
+/// m1 = c^dp mod p, m2 = c^dq mod q
+/// h  = qinv * (m1 - m2) mod p
+/// m  = m2 + h * q
+fn decrypt_crt(c: &Integer, crt: &CrtParams) -> Integer {
+    let m1 = c.clone().pow_mod(&crt.dp, &crt.p).unwrap();
+    let m2 = c.clone().pow_mod(&crt.dq, &crt.q).unwrap();
+
+    // `modulo` always returns a non-negative result for a positive
+    // modulus, so m1 - m2 being negative is not an issue here.
+    let diff = (m1 - m2.clone()).modulo(&crt.p);
+    let h = (crt.qinv.clone() * diff).modulo(&crt.p);
+
+    m2 + h * crt.q.clone()
 }
 
 #[cfg(test)]
@@ -133,10 +356,10 @@ mod tests {
         assert_eq!(d, 413);
 
         // 6. public key is (e = 17, n = 3233)
-        let public_key = PublicKey { e, n: n.clone() };
+        let public_key = PublicKey { e: e.clone(), n: n.clone() };
 
         // 7. private key is (d = 413, n = 3233)
-        let private_key = PrivateKey { d, n: n.clone() };
+        let private_key = PrivateKey { d, n: n.clone(), e, crt: None };
 
         // message, m = 65
         let m = Integer::from(65);
@@ -158,13 +381,15 @@ mod tests {
     #[test]
     fn rsa_primes_p_q_test() {
 
+        let mut rand_state = RandState::new();
+
         let nlen = 16;
         let min = rsa_fips_key_constraint_min(nlen);
         let max = rsa_fips_key_constraint_max(nlen);
         assert_eq!(min, 181);
         assert_eq!(max, 255);
 
-        let (p, q) = rsa_primes_p_q(nlen);
+        let (p, q) = rsa_primes_p_q(nlen, rsa_fips_mr_rounds(nlen), &mut rand_state);
         assert_ne!(p, q);
         assert!(min <= p);
         assert!(p <= max);
@@ -177,7 +402,7 @@ mod tests {
         assert_eq!(min, 3037000500_u64);
         assert_eq!(max, 4294967295_u64);
 
-        let (p, q) = rsa_primes_p_q(nlen);
+        let (p, q) = rsa_primes_p_q(nlen, rsa_fips_mr_rounds(nlen), &mut rand_state);
         assert_ne!(p, q);
         assert!(min <= p);
         assert!(p <= max);
@@ -190,7 +415,7 @@ mod tests {
         assert!(min.significant_bits() <= (nlen as u32 / 2));
         assert!(max.significant_bits() >= (nlen as u32 / 2));
 
-        let (p, q) = rsa_primes_p_q(nlen);
+        let (p, q) = rsa_primes_p_q(nlen, rsa_fips_mr_rounds(nlen), &mut rand_state);
         assert_ne!(p, q);
         assert!(min <= p);
         assert!(p <= max);
@@ -203,7 +428,7 @@ mod tests {
         assert!(min.significant_bits() <= (nlen as u32 / 2));
         assert!(max.significant_bits() >= (nlen as u32 / 2));
 
-        let (p, q) = rsa_primes_p_q(nlen);
+        let (p, q) = rsa_primes_p_q(nlen, rsa_fips_mr_rounds(nlen), &mut rand_state);
         assert_ne!(p, q);
         assert!(min <= p);
         assert!(p <= max);
@@ -216,7 +441,7 @@ mod tests {
         assert!(min.significant_bits() <= (nlen as u32 / 2));
         assert!(max.significant_bits() >= (nlen as u32 / 2));
 
-        let (p, q) = rsa_primes_p_q(nlen);
+        let (p, q) = rsa_primes_p_q(nlen, rsa_fips_mr_rounds(nlen), &mut rand_state);
         assert_ne!(p, q);
         assert!(min <= p);
         assert!(p <= max);
@@ -224,14 +449,14 @@ mod tests {
         assert!(q <= max);
 
         // disabled to prevent delays in the continuous integration process
-        /* 
+        /*
         let nlen = 16384;
         let min = rsa_fips_key_constraint_min(nlen);
         let max = rsa_fips_key_constraint_max(nlen);
         assert!(min.significant_bits() <= (nlen as u32 / 2));
         assert!(max.significant_bits() >= (nlen as u32 / 2));
 
-        let (p, q) = rsa_primes_p_q(nlen);
+        let (p, q) = rsa_primes_p_q(nlen, rsa_fips_mr_rounds(nlen), &mut rand_state);
         assert_ne!(p, q);
         assert!(min <= p);
         assert!(p <= max);
@@ -240,10 +465,23 @@ mod tests {
         */
     }
 
+    #[test]
+    fn rsa_fips_mr_rounds_is_monotonically_non_increasing() {
+        // for a fixed error probability, smaller candidates need at least as
+        // many Miller-Rabin rounds as larger ones
+        let boundaries = [0u16, 511, 512, 767, 768, 1023, 1024, 1535, 1536, 4096];
+        let mut previous = rsa_fips_mr_rounds(boundaries[0] * 2);
+        for &half in &boundaries[1..] {
+            let rounds = rsa_fips_mr_rounds(half * 2);
+            assert!(rounds <= previous, "rounds increased at nlen/2 = {half}");
+            previous = rounds;
+        }
+    }
+
     #[test]
     fn rsa_test() {
         let nlen = 2048;
-        let (public_key, private_key) = generate_keys(nlen);
+        let (public_key, private_key) = generate_keys(nlen, None, None);
 
         // message, m = 65
         let m = Integer::from(12345);
@@ -259,4 +497,54 @@ mod tests {
 
         assert_eq!(m, dm);
     }
+
+    #[test]
+    fn decrypt_crt_matches_textbook_2048() {
+        let nlen = 2048;
+        let (public_key, private_key) = generate_keys(nlen, None, None);
+
+        let m = Integer::from(12345);
+        let c = encrypt(m.clone(), &public_key);
+
+        // the default key carries CRT parameters, so this goes through decrypt_crt
+        let crt_decrypted = decrypt(c.clone(), &private_key);
+
+        // strip the CRT parameters to force the textbook pow_mod path
+        let textbook_key = PrivateKey {
+            d: private_key.d.clone(),
+            n: private_key.n.clone(),
+            e: private_key.e.clone(),
+            crt: None,
+        };
+        let textbook_decrypted = decrypt(c, &textbook_key);
+
+        assert_eq!(crt_decrypted, textbook_decrypted);
+        assert_eq!(crt_decrypted, m);
+    }
+
+    #[test]
+    fn generate_keys_with_seed_is_reproducible() {
+        let nlen = 1024;
+
+        let (public_key_1, private_key_1) = generate_keys(nlen, Some(5), Some(42));
+        let (public_key_2, private_key_2) = generate_keys(nlen, Some(5), Some(42));
+
+        assert_eq!(public_key_1.n, public_key_2.n);
+        assert_eq!(private_key_1.n, private_key_2.n);
+        assert_eq!(private_key_1.d, private_key_2.d);
+    }
+
+    #[test]
+    fn cloned_private_key_outlives_the_original() {
+        let nlen = 1024;
+        let (public_key, private_key) = generate_keys(nlen, None, None);
+        let cloned_key = private_key.clone();
+
+        // dropping the original must not affect the independently-owned clone
+        drop(private_key);
+
+        let m = Integer::from(12345);
+        let c = encrypt(m.clone(), &public_key);
+        assert_eq!(decrypt(c, &cloned_key), m);
+    }
 }